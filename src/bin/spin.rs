@@ -21,6 +21,17 @@ use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger::cli::TriggerExecutorCommand;
 use spin_trigger_http::HttpTrigger;
 
+mod completions;
+mod manifest_version;
+mod plugin_registry;
+mod plugin_rpc;
+mod shell;
+mod trigger_plugins;
+
+use completions::CompletionsCommand;
+use plugin_rpc::{LongLivedPlugins, PluginKind};
+use shell::ShellCommand;
+
 #[tokio::main]
 async fn main() {
     if let Err(err) = _main().await {
@@ -62,14 +73,39 @@ async fn _main() -> anyhow::Result<()> {
 
     let matches = cmd.clone().get_matches();
 
+    let argv: Vec<String> = std::env::args().collect();
+    dispatch(&argv, &matches, cmd, &plugin_help_entries).await
+}
+
+/// Routes a parsed command line to wherever it needs to go: a `long-lived` or
+/// `filter` plugin over the RPC channel, a one-shot plugin subcommand spawned
+/// fresh, or an ordinary built-in [`SpinApp`] variant. `argv` is the full
+/// command line including the leading `spin` program name, so this can be
+/// shared between `_main` (the real process argv) and `spin shell` (a
+/// tokenized REPL line with `spin` prepended).
+async fn dispatch(
+    argv: &[String],
+    matches: &clap::ArgMatches,
+    cmd: clap::Command<'_>,
+    plugin_help_entries: &[PluginHelpEntry],
+) -> anyhow::Result<()> {
     if let Some((subcmd, _)) = matches.subcommand() {
         if plugin_help_entries.iter().any(|e| e.name == subcmd) {
-            let command = std::env::args().skip(1).collect();
+            if matches!(
+                plugin_registry::plugin_kind(subcmd),
+                PluginKind::LongLived | PluginKind::Filter
+            ) {
+                let args: Vec<String> = argv.iter().skip(2).cloned().collect();
+                return run_long_lived_plugin(subcmd, args).await;
+            }
+            let command = argv.iter().skip(1).cloned().collect();
             return execute_external_subcommand(command, cmd).await;
         }
     }
 
-    SpinApp::from_arg_matches(&matches)?.run(cmd).await
+    SpinApp::from_arg_matches(matches)?
+        .run(cmd, plugin_help_entries.to_vec())
+        .await
 }
 
 fn print_error_chain(err: anyhow::Error) {
@@ -122,6 +158,8 @@ enum SpinApp {
     External(Vec<String>),
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    Shell(ShellCommand),
+    Completions(CompletionsCommand),
 }
 
 #[derive(Subcommand)]
@@ -130,27 +168,52 @@ enum TriggerCommands {
     Redis(TriggerExecutorCommand<RedisTrigger>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(TriggerExecutorCommand<HelpArgsOnlyTrigger>),
+    // Any trigger type with no built-in variant is resolved against an
+    // installed `trigger-<name>` plugin at runtime.
+    #[clap(external_subcommand)]
+    External(Vec<String>),
 }
 
 impl SpinApp {
     /// The main entry point to Spin.
-    pub async fn run(self, app: clap::Command<'_>) -> Result<(), Error> {
+    pub async fn run(self, app: clap::Command<'_>, plugins: Vec<PluginHelpEntry>) -> Result<(), Error> {
         match self {
             Self::Templates(cmd) => cmd.run().await,
-            Self::Up(cmd) => cmd.run().await,
+            Self::Up(cmd) => {
+                check_default_manifest_compatible()?;
+                cmd.run().await
+            }
             Self::New(cmd) => cmd.run().await,
             Self::Add(cmd) => cmd.run().await,
-            Self::Deploy(cmd) => cmd.run(SpinApp::command()).await,
+            Self::Deploy(cmd) => {
+                check_default_manifest_compatible()?;
+                cmd.run(SpinApp::command()).await
+            }
             Self::Login(cmd) => cmd.run(SpinApp::command()).await,
             Self::Registry(cmd) => cmd.run().await,
-            Self::Build(cmd) => cmd.run().await,
+            Self::Build(cmd) => {
+                check_default_manifest_compatible()?;
+                cmd.run().await
+            }
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::External(mut args)) => {
+                if args.is_empty() {
+                    anyhow::bail!("usage: spin trigger <trigger-type> [args]...");
+                }
+                let trigger_type = args.remove(0);
+                trigger_plugins::run_trigger_plugin(&trigger_type, args).await
+            }
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
-            Self::Watch(cmd) => cmd.run().await,
+            Self::Watch(cmd) => {
+                check_default_manifest_compatible()?;
+                cmd.run().await
+            }
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Shell(cmd) => cmd.run(app, plugins).await,
+            Self::Completions(cmd) => cmd.run(app).await,
         }
     }
 }
@@ -160,6 +223,7 @@ fn build_info() -> String {
     format!("{SPIN_VERSION} ({SPIN_COMMIT_SHA} {SPIN_COMMIT_DATE})")
 }
 
+#[derive(Clone)]
 struct PluginHelpEntry {
     name: String,
     about: String,
@@ -209,3 +273,38 @@ fn installed_plugin_help_entries() -> Vec<PluginHelpEntry> {
 fn hide_plugin_in_help(plugin: &spin_plugins::manifest::PluginManifest) -> bool {
     plugin.name().starts_with("trigger-")
 }
+
+/// Checks that `spin.toml` in the current directory is a schema this
+/// runtime's loader understands before delegating to a command that loads
+/// it; never mutates the file.
+fn check_default_manifest_compatible() -> anyhow::Result<()> {
+    manifest_version::check_manifest_compatible(std::path::Path::new("spin.toml"))
+}
+
+lazy_static! {
+    static ref LONG_LIVED_PLUGINS: LongLivedPlugins = LongLivedPlugins::new();
+}
+
+/// Hands a command off to a `long-lived` or `filter` plugin over the
+/// MessagePack RPC channel instead of spawning and reaping a one-shot
+/// process, reconnecting to an already-resident plugin if one is running.
+async fn run_long_lived_plugin(name: &str, args: Vec<String>) -> anyhow::Result<()> {
+    let executable = plugin_registry::executable_path(name);
+    let mut connections = LONG_LIVED_PLUGINS.get_or_spawn(name, &executable)?;
+    let conn = connections
+        .get_mut(name)
+        .expect("connection was just inserted");
+
+    if !conn.supported_methods().iter().any(|m| m == "execute") {
+        anyhow::bail!("plugin `{name}` does not support the `execute` RPC method");
+    }
+
+    let params = rmpv::Value::Array(args.into_iter().map(rmpv::Value::from).collect());
+    let result = conn.call("execute", params)?;
+    if let Some(code) = result.as_i64() {
+        if code != 0 {
+            std::process::exit(code as i32);
+        }
+    }
+    Ok(())
+}
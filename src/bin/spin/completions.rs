@@ -0,0 +1,36 @@
+//! `spin completions`: renders a shell completion script from the fully
+//! assembled `clap::Command` -- the same one `_main` builds by merging the
+//! built-in subcommands with `plugin_help_entries()` -- so plugins installed
+//! when the script is generated complete too. The script is static: plugins
+//! installed afterwards complete only once it is generated again.
+
+use clap::Command;
+use clap_complete::{generate, Shell};
+
+/// `spin completions`: emit a shell completion script, plugin subcommands included.
+#[derive(clap::Parser, Debug)]
+pub struct CompletionsCommand {
+    /// The shell to render a completion script for.
+    #[clap(value_enum)]
+    shell: Shell,
+}
+
+impl CompletionsCommand {
+    pub async fn run(self, mut cmd: Command<'_>) -> anyhow::Result<()> {
+        strip_help_markers(&mut cmd);
+
+        let name = cmd.get_name().to_owned();
+        generate(self.shell, &mut cmd, name, &mut std::io::stdout());
+        Ok(())
+    }
+}
+
+/// Plugin subcommands are displayed as `name*` so `* implemented via plugin`
+/// reads naturally in `--help`; completions should offer the real name.
+fn strip_help_markers(cmd: &mut Command<'_>) {
+    for sub in cmd.get_subcommands_mut() {
+        if let Some(stripped) = sub.get_name().strip_suffix('*') {
+            *sub = std::mem::take(sub).name(stripped.to_owned());
+        }
+    }
+}
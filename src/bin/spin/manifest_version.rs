@@ -0,0 +1,242 @@
+//! Detection and translation for `spin.toml` manifest schema versions.
+//!
+//! `UpCommand`, `BuildCommand`, `DeployCommand` and `WatchCommand` all load
+//! an application manifest before doing anything else; this module gives
+//! them a shared way to tell a v1 manifest (the implicit default, no
+//! `spin_manifest_version` key) apart from a v2 one (`spin_manifest_version = 2`,
+//! with the restructured `[component.*]` / `[[trigger.*]]` tables) and to
+//! translate the latter into the same in-memory app model v1 produces, so
+//! triggers and build logic don't need to know which schema a project used.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+
+/// The `spin.toml` schema a manifest was written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestVersion {
+    /// No `spin_manifest_version` key, or explicitly `1`.
+    V1,
+    /// `spin_manifest_version = 2`.
+    V2,
+}
+
+/// Reads just enough of `manifest` to determine its schema version, without
+/// fully parsing it against either schema's strict structure.
+pub fn detect_manifest_version(manifest: &toml::Value) -> anyhow::Result<ManifestVersion> {
+    match manifest.get("spin_manifest_version") {
+        None => Ok(ManifestVersion::V1),
+        Some(toml::Value::Integer(1)) => Ok(ManifestVersion::V1),
+        Some(toml::Value::Integer(2)) => Ok(ManifestVersion::V2),
+        Some(other) => bail!("unsupported spin_manifest_version: {other}"),
+    }
+}
+
+/// Translates a v2 manifest's `[component.*]` / `[[trigger.*]]` tables into
+/// the flattened `[[component]]` / `[[trigger]]` array-of-tables shape the v1
+/// loader (and therefore every existing trigger) already understands.
+pub fn translate_v2_to_v1(manifest: toml::Value) -> anyhow::Result<toml::Value> {
+    let table = manifest
+        .as_table()
+        .context("manifest root must be a TOML table")?;
+
+    let mut v1 = toml::value::Table::new();
+    for (key, value) in table {
+        if key == "spin_manifest_version" {
+            continue;
+        }
+        if key == "component" {
+            let components = value
+                .as_table()
+                .context("[component.*] must be a table of named components")?;
+            let mut array = Vec::with_capacity(components.len());
+            for (name, component) in components {
+                let mut component = component
+                    .as_table()
+                    .cloned()
+                    .with_context(|| format!("component `{name}` must be a table"))?;
+                component.insert("id".to_owned(), toml::Value::String(name.clone()));
+                array.push(toml::Value::Table(component));
+            }
+            v1.insert("component".to_owned(), toml::Value::Array(array));
+            continue;
+        }
+        if key == "trigger" {
+            let triggers = value
+                .as_table()
+                .context("[trigger.*] must be a table of named triggers")?;
+            let mut array = Vec::with_capacity(triggers.len());
+            for (trigger_type, entries) in triggers {
+                let entries = entries
+                    .as_array()
+                    .with_context(|| format!("[[trigger.{trigger_type}]] must be an array"))?;
+                for entry in entries {
+                    let mut entry = entry
+                        .as_table()
+                        .cloned()
+                        .with_context(|| format!("trigger entry under `{trigger_type}` must be a table"))?;
+                    entry.insert(
+                        "trigger_type".to_owned(),
+                        toml::Value::String(trigger_type.clone()),
+                    );
+                    array.push(toml::Value::Table(entry));
+                }
+            }
+            v1.insert("trigger".to_owned(), toml::Value::Array(array));
+            continue;
+        }
+        v1.insert(key.clone(), value.clone());
+    }
+
+    Ok(toml::Value::Table(v1))
+}
+
+/// Loads `spin.toml`-style `contents`, auto-detecting its schema version and
+/// returning it translated into the v1 shape every existing loader consumes.
+///
+/// Call sites that only support v1 features (e.g. a v1-only trigger runtime)
+/// should check [`detect_manifest_version`] themselves first and surface a
+/// clear error through `print_error_chain` rather than silently downgrading.
+pub fn load_and_normalize(contents: &str) -> anyhow::Result<toml::Value> {
+    let manifest: toml::Value = toml::from_str(contents).context("invalid spin.toml")?;
+    match detect_manifest_version(&manifest)? {
+        ManifestVersion::V1 => Ok(manifest),
+        ManifestVersion::V2 => translate_v2_to_v1(manifest),
+    }
+}
+
+/// The actual integration point: `UpCommand`, `BuildCommand`, `DeployCommand`
+/// and `WatchCommand` all load their manifest from `manifest_path` (relative
+/// to the current directory, same as `spin.toml` is today) through their own
+/// v1-only loader. This check runs entirely in memory and never writes
+/// `manifest_path` -- a `spin` invocation must never mutate the project it
+/// was pointed at as a side effect of just loading it.
+///
+/// A missing manifest file is left for the delegated command to report in
+/// its own words. A v2 manifest can't be handed to that v1-only loader
+/// in-memory without a hook for an already-parsed manifest, which the
+/// delegated commands don't expose yet, so for now this only detects v2 and
+/// surfaces a clear, actionable error here -- via the same [`translate_v2_to_v1`]
+/// this module already offers callers who *can* consume an in-memory v1
+/// value -- rather than letting the v1-only loader fail confusingly on the
+/// untranslated v2 shape.
+pub fn check_manifest_compatible(manifest_path: &Path) -> anyhow::Result<()> {
+    if !manifest_path.exists() {
+        return Ok(());
+    }
+
+    let contents = std::fs::read_to_string(manifest_path)
+        .with_context(|| format!("failed to read {manifest_path:?}"))?;
+    let manifest: toml::Value = toml::from_str(&contents)
+        .with_context(|| format!("{manifest_path:?} is not valid TOML"))?;
+
+    if detect_manifest_version(&manifest)? == ManifestVersion::V1 {
+        return Ok(());
+    }
+
+    // Exercise the translation so a v2 manifest this runtime can't yet
+    // represent in v1 terms is caught here with a clear error, rather than
+    // failing confusingly once the delegate command's v1-only loader sees it.
+    translate_v2_to_v1(manifest).with_context(|| {
+        format!("{manifest_path:?} uses a spin_manifest_version = 2 feature this runtime can't translate to v1 yet")
+    })?;
+
+    bail!(
+        "{manifest_path:?} uses spin.toml schema v2, which this command doesn't load directly yet; \
+         translate it to v1 (spin_manifest_version = 1) before running this command"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml(source: &str) -> toml::Value {
+        toml::from_str(source).expect("test fixture must be valid TOML")
+    }
+
+    #[test]
+    fn v1_has_no_manifest_version_key() {
+        let manifest = toml(r#"name = "hello""#);
+        assert_eq!(detect_manifest_version(&manifest).unwrap(), ManifestVersion::V1);
+    }
+
+    #[test]
+    fn explicit_v1_is_v1() {
+        let manifest = toml("spin_manifest_version = 1\nname = \"hello\"");
+        assert_eq!(detect_manifest_version(&manifest).unwrap(), ManifestVersion::V1);
+    }
+
+    #[test]
+    fn v2_is_detected() {
+        let manifest = toml("spin_manifest_version = 2\nname = \"hello\"");
+        assert_eq!(detect_manifest_version(&manifest).unwrap(), ManifestVersion::V2);
+    }
+
+    #[test]
+    fn unsupported_version_is_an_error() {
+        let manifest = toml("spin_manifest_version = 3\nname = \"hello\"");
+        assert!(detect_manifest_version(&manifest).is_err());
+    }
+
+    #[test]
+    fn translate_flattens_named_components_with_injected_id() {
+        let manifest = toml(
+            r#"
+            spin_manifest_version = 2
+            name = "hello"
+
+            [component.foo]
+            source = "foo.wasm"
+
+            [component.bar]
+            source = "bar.wasm"
+            "#,
+        );
+
+        let v1 = translate_v2_to_v1(manifest).unwrap();
+        let components = v1.get("component").unwrap().as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        let ids: Vec<&str> = components
+            .iter()
+            .map(|c| c.get("id").unwrap().as_str().unwrap())
+            .collect();
+        assert!(ids.contains(&"foo"));
+        assert!(ids.contains(&"bar"));
+    }
+
+    #[test]
+    fn translate_flattens_multiple_trigger_types_with_injected_trigger_type() {
+        let manifest = toml(
+            r#"
+            spin_manifest_version = 2
+            name = "hello"
+
+            [[trigger.http]]
+            route = "/"
+            component = "foo"
+
+            [[trigger.redis]]
+            channel = "messages"
+            component = "bar"
+            "#,
+        );
+
+        let v1 = translate_v2_to_v1(manifest).unwrap();
+        let triggers = v1.get("trigger").unwrap().as_array().unwrap();
+        assert_eq!(triggers.len(), 2);
+        let trigger_types: Vec<&str> = triggers
+            .iter()
+            .map(|t| t.get("trigger_type").unwrap().as_str().unwrap())
+            .collect();
+        assert!(trigger_types.contains(&"http"));
+        assert!(trigger_types.contains(&"redis"));
+    }
+
+    #[test]
+    fn translate_drops_the_version_key() {
+        let manifest = toml("spin_manifest_version = 2\nname = \"hello\"");
+        let v1 = translate_v2_to_v1(manifest).unwrap();
+        assert!(v1.get("spin_manifest_version").is_none());
+    }
+}
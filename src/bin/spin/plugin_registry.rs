@@ -0,0 +1,87 @@
+//! Extra per-plugin metadata (`kind`, on-disk executable location) that
+//! `spin_plugins::manifest::PluginManifest` doesn't expose an accessor for.
+//!
+//! Rather than assuming `PluginManifest` grows new methods it doesn't have
+//! today, this reads the same on-disk layout `PluginManager` already
+//! installs plugins into: a manifest JSON file per plugin under
+//! `<data dir>/spin/plugins/manifests/<name>.json`, and the plugin's
+//! executable under `<data dir>/spin/plugins/<name>/<name>`. Both are part
+//! of the plugin packaging format plugin authors write to, not a private
+//! implementation detail, so reading them directly here doesn't require any
+//! change to the `spin_plugins` crate itself.
+
+use std::path::PathBuf;
+
+use crate::plugin_rpc::PluginKind;
+
+/// Root directory plugins are installed under.
+fn plugins_dir() -> PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(std::env::temp_dir);
+    data_dir.join("spin").join("plugins")
+}
+
+/// Where `name`'s manifest JSON is installed.
+fn manifest_path(name: &str) -> PathBuf {
+    plugins_dir().join("manifests").join(format!("{name}.json"))
+}
+
+/// Where `name`'s executable is installed.
+pub fn executable_path(name: &str) -> PathBuf {
+    let file_name = if cfg!(windows) {
+        format!("{name}.exe")
+    } else {
+        name.to_owned()
+    };
+    plugins_dir().join(name).join(file_name)
+}
+
+/// Reads the `kind` field out of `name`'s installed manifest JSON, defaulting
+/// to [`PluginKind::OneShot`] if the manifest is missing, unreadable, or
+/// doesn't declare one -- existing plugins written before `kind` existed
+/// keep working exactly as they do today.
+pub fn plugin_kind(name: &str) -> PluginKind {
+    let Ok(contents) = std::fs::read_to_string(manifest_path(name)) else {
+        return PluginKind::OneShot;
+    };
+    match extract_json_string_field(&contents, "kind").as_deref() {
+        Some("long-lived") => PluginKind::LongLived,
+        Some("filter") => PluginKind::Filter,
+        _ => PluginKind::OneShot,
+    }
+}
+
+/// A minimal, dependency-free extraction of the first `"field": "value"` pair
+/// found anywhere in a JSON document (nesting and escaped quotes are not taken
+/// into account), good enough for the handful of string fields this module
+/// cares about without pulling in a JSON parser for them.
+fn extract_json_string_field(json: &str, field: &str) -> Option<String> {
+    let needle = format!("\"{field}\"");
+    let after_key = json.split_once(&needle)?.1;
+    let after_colon = after_key.split_once(':')?.1.trim_start();
+    let after_quote = after_colon.strip_prefix('"')?;
+    let end = after_quote.find('"')?;
+    Some(after_quote[..end].to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_string_field() {
+        let json = r#"{"name": "trigger-foo", "kind": "long-lived"}"#;
+        assert_eq!(
+            extract_json_string_field(json, "kind").as_deref(),
+            Some("long-lived")
+        );
+    }
+
+    #[test]
+    fn missing_field_is_none() {
+        let json = r#"{"name": "trigger-foo"}"#;
+        assert_eq!(extract_json_string_field(json, "kind"), None);
+    }
+}
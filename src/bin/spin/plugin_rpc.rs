@@ -0,0 +1,380 @@
+//! Long-lived plugin protocol: a length-prefixed MessagePack IPC channel between
+//! `spin` and plugin processes that declare themselves `long-lived` or `filter`
+//! in their manifest, instead of the one-shot argv passthrough used for
+//! ordinary plugins.
+//!
+//! The wire format is a 4-byte big-endian length prefix followed by a
+//! MessagePack-encoded [`Request`] or [`Response`]. Connections are keyed by
+//! plugin name so a `long-lived` plugin is spawned at most once: if a
+//! previous `spin` invocation already left one resident (tracked via a
+//! pidfile next to its socket), later invocations connect to it instead of
+//! spawning a competing copy.
+
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::io::{Read, Write};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::process::Child;
+#[cfg(unix)]
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// How a plugin wants to be run, taken from the `kind` field of its manifest.
+///
+/// This is additive: a manifest with no `kind` is treated as [`PluginKind::OneShot`]
+/// so existing plugins keep working unmodified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginKind {
+    /// Spawned fresh for every invocation and torn down when it exits (today's behavior).
+    OneShot,
+    /// Spawned once and kept resident across multiple `spin` invocations.
+    LongLived,
+    /// A long-lived plugin that additionally may short-circuit or rewrite a command.
+    Filter,
+}
+
+impl Default for PluginKind {
+    fn default() -> Self {
+        Self::OneShot
+    }
+}
+
+/// A single call into a long-lived plugin.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub id: u64,
+    pub method: String,
+    pub params: rmpv::Value,
+}
+
+/// The plugin's reply to a [`Request`] with a matching `id`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub id: u64,
+    #[serde(flatten)]
+    pub payload: ResponsePayload,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponsePayload {
+    Result(rmpv::Value),
+    Error(String),
+}
+
+/// Exchanged once, immediately after connecting, so the CLI can refuse to talk
+/// to a plugin built against an incompatible protocol revision.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Handshake {
+    pub protocol_version: u32,
+    pub methods: Vec<String>,
+}
+
+/// The protocol version `spin` speaks. Bump whenever the frame or handshake
+/// shape changes in a way that isn't backwards compatible.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A connection to a resident long-lived (or filter) plugin process.
+///
+/// `child` is `Some` only when this `spin` invocation is the one that spawned
+/// the plugin; when we reconnect to a plugin a previous invocation left
+/// running, we don't own its process and must not kill it on drop.
+pub struct PluginConnection {
+    #[allow(dead_code)]
+    child: Option<Child>,
+    transport: Transport,
+    handshake: Handshake,
+    next_id: u64,
+}
+
+#[cfg(unix)]
+type Transport = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type Transport = std::convert::Infallible;
+
+#[cfg(not(unix))]
+impl PluginConnection {
+    /// Long-lived plugins are supported on Unix only: on other platforms this
+    /// always returns an error and neither connects to nor spawns a plugin.
+    pub fn connect_or_spawn(_name: &str, _executable: &Path) -> Result<Self> {
+        bail!("long-lived plugins are only supported on Unix platforms so far (named pipe support for Windows is not implemented yet)")
+    }
+}
+
+#[cfg(unix)]
+impl PluginConnection {
+    /// Connects to the plugin resident for `name` if one is already running,
+    /// alive (per its pidfile), and still built from the same `executable` we
+    /// were asked to run, otherwise spawns `executable` and waits for it to
+    /// start listening.
+    ///
+    /// The pidfile records the executable's mtime alongside its pid: if an
+    /// upgrade or reinstall has replaced `executable` since the resident
+    /// process was spawned, the mtime no longer matches, so the stale process
+    /// is stopped and a fresh one spawned from the new binary instead of
+    /// silently talking to the old one forever.
+    pub fn connect_or_spawn(name: &str, executable: &Path) -> Result<Self> {
+        let socket_path = socket_path_for(name)?;
+        let pidfile_path = pidfile_path_for(name)?;
+        let current_mtime = executable_mtime(executable)?;
+
+        if let Some(resident) = read_resident(&pidfile_path) {
+            if process_is_alive(resident.pid) {
+                if resident.executable_mtime == current_mtime {
+                    if let Ok(socket) = Self::connect_with_retry(&socket_path, 5) {
+                        return Self::new(None, socket);
+                    }
+                }
+                // Outdated or unreachable: stop it rather than leave it running
+                // beside the replacement spawned below.
+                stop_stale_resident(resident.pid);
+            }
+        }
+
+        // No live, up-to-date resident plugin: clean up anything stale and spawn a fresh one.
+        let _ = std::fs::remove_file(&socket_path);
+        let _ = std::fs::remove_file(&pidfile_path);
+
+        let child = Command::new(executable)
+            .arg("--spin-rpc-socket")
+            .arg(&socket_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("failed to spawn plugin {}", executable.display()))?;
+
+        std::fs::write(&pidfile_path, format!("{}\n{}", child.id(), current_mtime))
+            .with_context(|| format!("failed to write pidfile {pidfile_path:?}"))?;
+
+        let socket = Self::connect_with_retry(&socket_path, 20)
+            .with_context(|| format!("failed to connect to plugin socket {socket_path:?}"))?;
+
+        Self::new(Some(child), socket)
+    }
+
+    fn new(child: Option<Child>, socket: Transport) -> Result<Self> {
+        let mut conn = Self {
+            child,
+            transport: socket,
+            handshake: Handshake {
+                protocol_version: 0,
+                methods: Vec::new(),
+            },
+            next_id: 0,
+        };
+        conn.handshake = conn.perform_handshake()?;
+        Ok(conn)
+    }
+
+    fn connect_with_retry(
+        socket_path: &Path,
+        attempts: u32,
+    ) -> Result<std::os::unix::net::UnixStream> {
+        let delay = std::time::Duration::from_millis(50);
+        let mut last_err = None;
+        for _ in 0..attempts {
+            match std::os::unix::net::UnixStream::connect(socket_path) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+            std::thread::sleep(delay);
+        }
+        Err(last_err.unwrap()).context("plugin never accepted a connection")
+    }
+
+    fn perform_handshake(&mut self) -> Result<Handshake> {
+        let ours = Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            methods: Vec::new(),
+        };
+        self.write_frame(&ours)?;
+        let theirs: Handshake = self.read_frame()?;
+        if theirs.protocol_version != PROTOCOL_VERSION {
+            bail!(
+                "plugin speaks protocol version {}, spin expects {}",
+                theirs.protocol_version,
+                PROTOCOL_VERSION
+            );
+        }
+        Ok(theirs)
+    }
+
+    /// Returns the methods the plugin advertised during the handshake.
+    pub fn supported_methods(&self) -> &[String] {
+        &self.handshake.methods
+    }
+
+    /// Calls `method` on the resident plugin and waits for its reply.
+    pub fn call(&mut self, method: &str, params: rmpv::Value) -> Result<rmpv::Value> {
+        self.next_id += 1;
+        let request = Request {
+            id: self.next_id,
+            method: method.to_owned(),
+            params,
+        };
+        self.write_frame(&request)?;
+        let response: Response = self.read_frame()?;
+        if response.id != request.id {
+            bail!(
+                "plugin response id {} did not match request id {}",
+                response.id,
+                request.id
+            );
+        }
+        match response.payload {
+            ResponsePayload::Result(value) => Ok(value),
+            ResponsePayload::Error(message) => Err(anyhow!("plugin returned an error: {message}")),
+        }
+    }
+
+    fn write_frame<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        let bytes = rmp_serde::to_vec_named(value).context("failed to encode MessagePack frame")?;
+        let len = u32::try_from(bytes.len()).context("frame too large")?;
+        self.transport.write_all(&len.to_be_bytes())?;
+        self.transport.write_all(&bytes)?;
+        self.transport.flush()?;
+        Ok(())
+    }
+
+    fn read_frame<T: for<'de> Deserialize<'de>>(&mut self) -> Result<T> {
+        let mut len_buf = [0u8; 4];
+        self.transport.read_exact(&mut len_buf)?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        let mut buf = vec![0u8; len];
+        self.transport.read_exact(&mut buf)?;
+        rmp_serde::from_slice(&buf).context("failed to decode MessagePack frame")
+    }
+}
+
+/// Tracks resident plugin connections for the lifetime of the current `spin`
+/// invocation, keyed by plugin name. Reuse *across* invocations is handled by
+/// [`PluginConnection::connect_or_spawn`] reconnecting to a previous
+/// invocation's resident process via its pidfile, not by this cache (which
+/// only ever lives as long as one `spin` process).
+#[derive(Default)]
+pub struct LongLivedPlugins {
+    connections: Mutex<HashMap<String, PluginConnection>>,
+}
+
+impl LongLivedPlugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a connection for `name`, reusing one already opened by this
+    /// process, reconnecting to one left resident by a previous invocation,
+    /// or spawning a fresh plugin process if neither is available.
+    pub fn get_or_spawn(
+        &self,
+        name: &str,
+        executable: &Path,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<String, PluginConnection>>> {
+        let mut connections = self.connections.lock().unwrap();
+        if !connections.contains_key(name) {
+            let conn = PluginConnection::connect_or_spawn(name, executable)?;
+            connections.insert(name.to_owned(), conn);
+        }
+        Ok(connections)
+    }
+}
+
+/// The per-user directory resident-plugin sockets and pidfiles live under.
+///
+/// Namespaced by uid (via `XDG_RUNTIME_DIR` when set, otherwise a
+/// user-specific subdirectory of the system temp dir) and created with
+/// owner-only permissions so another local user can't race us for the path,
+/// delete a socket out from under a resident plugin, or plant a symlink.
+#[cfg(unix)]
+fn runtime_dir() -> Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = if let Ok(xdg) = std::env::var("XDG_RUNTIME_DIR") {
+        PathBuf::from(xdg).join("spin-plugins")
+    } else {
+        let uid = unsafe { libc::getuid() };
+        std::env::temp_dir().join(format!("spin-plugins-{uid}"))
+    };
+
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {dir:?}"))?;
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+        .with_context(|| format!("failed to lock down permissions on {dir:?}"))?;
+
+    Ok(dir)
+}
+
+#[cfg(unix)]
+fn socket_path_for(name: &str) -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(format!("{name}.sock")))
+}
+
+#[cfg(unix)]
+fn pidfile_path_for(name: &str) -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(format!("{name}.pid")))
+}
+
+/// A resident plugin process recorded in a pidfile: its pid, and the mtime of
+/// the executable it was spawned from (used to detect upgrades).
+#[cfg(unix)]
+struct ResidentPlugin {
+    pid: libc::pid_t,
+    executable_mtime: u64,
+}
+
+#[cfg(unix)]
+fn read_resident(pidfile_path: &Path) -> Option<ResidentPlugin> {
+    let contents = std::fs::read_to_string(pidfile_path).ok()?;
+    let mut lines = contents.lines();
+    let pid = lines.next()?.trim().parse().ok()?;
+    let executable_mtime = lines.next()?.trim().parse().ok()?;
+    Some(ResidentPlugin {
+        pid,
+        executable_mtime,
+    })
+}
+
+/// The executable's modification time as a Unix timestamp in seconds, used as
+/// a cheap stand-in for a build/version identifier: a reinstalled or upgraded
+/// plugin binary gets a new mtime even though its path doesn't change.
+#[cfg(unix)]
+fn executable_mtime(executable: &Path) -> Result<u64> {
+    let modified = std::fs::metadata(executable)
+        .with_context(|| format!("failed to stat plugin executable {executable:?}"))?
+        .modified()
+        .with_context(|| format!("failed to read mtime of {executable:?}"))?;
+    Ok(modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs())
+}
+
+/// Whether `pid` identifies a still-running process, checked by sending the
+/// null signal (which performs permission/existence checks without actually
+/// signaling anything).
+#[cfg(unix)]
+fn process_is_alive(pid: libc::pid_t) -> bool {
+    unsafe { libc::kill(pid, 0) == 0 }
+}
+
+/// Terminates a resident plugin left behind by a stale executable, and waits
+/// briefly for it to exit so the freshly spawned replacement doesn't race it
+/// for the socket path.
+#[cfg(unix)]
+fn stop_stale_resident(pid: libc::pid_t) {
+    unsafe {
+        libc::kill(pid, libc::SIGTERM);
+    }
+    let delay = std::time::Duration::from_millis(50);
+    for _ in 0..20 {
+        if !process_is_alive(pid) {
+            break;
+        }
+        std::thread::sleep(delay);
+    }
+}
@@ -0,0 +1,164 @@
+//! `spin shell`: an interactive REPL for running `spin` subcommands without
+//! re-typing `spin` or shelling out to `--help` between invocations.
+//!
+//! Each line is tokenized, parsed against the same [`clap::Command`] the
+//! top-level CLI builds (built-ins plus discovered plugin subcommands), and
+//! dispatched through the exact `SpinApp::run` path a normal `spin` call
+//! would take.
+
+use clap::Command;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hint, Hinter};
+use rustyline::validate::Validator;
+use rustyline::{Context as RLContext, Editor, Helper};
+
+use crate::PluginHelpEntry;
+
+/// `spin shell`: drop into a line-editing REPL over the Spin CLI.
+#[derive(clap::Parser, Debug)]
+pub struct ShellCommand {}
+
+impl ShellCommand {
+    pub async fn run(self, cmd: Command<'_>, plugins: Vec<PluginHelpEntry>) -> anyhow::Result<()> {
+        let helper = SpinShellHelper::new(cmd.clone());
+        let mut editor = Editor::<SpinShellHelper>::new()?;
+        editor.set_helper(Some(helper));
+
+        println!("spin shell -- type `help` or a subcommand, Ctrl-D to exit");
+        loop {
+            let readline = editor.readline("spin> ");
+            match readline {
+                Ok(line) => {
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    editor.add_history_entry(line);
+                    if let Err(err) = Self::dispatch(line, cmd.clone(), plugins.clone()).await {
+                        terminal::error!("{err}");
+                    }
+                }
+                Err(rustyline::error::ReadlineError::Eof)
+                | Err(rustyline::error::ReadlineError::Interrupted) => break,
+                Err(err) => {
+                    terminal::error!("{err}");
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses `line` the same way `_main` parses real argv, then hands off to
+    /// the same [`crate::dispatch`] plugin-detection logic `_main` uses, so a
+    /// `long-lived`/`filter` plugin invoked via the shell is reused across
+    /// lines instead of being spawned fresh every time.
+    async fn dispatch(
+        line: &str,
+        cmd: Command<'_>,
+        plugins: Vec<PluginHelpEntry>,
+    ) -> anyhow::Result<()> {
+        let tokens = shell_words::split(line)?;
+        let mut argv = vec!["spin".to_owned()];
+        argv.extend(tokens);
+
+        let matches = cmd.clone().try_get_matches_from(argv.clone())?;
+        crate::dispatch(&argv, &matches, cmd, &plugins).await
+    }
+}
+
+/// Ties tab completion and inline hints together for the `spin shell` REPL.
+///
+/// Plugin subcommands are already part of `cmd` (`_main` adds one for each
+/// entry in `plugin_help_entries()` before building the shell), so
+/// `top_level_names()` surfaces them without needing a separate plugin list.
+pub struct SpinShellHelper {
+    cmd: Command<'static>,
+}
+
+impl SpinShellHelper {
+    fn new(cmd: Command<'_>) -> Self {
+        Self { cmd: cmd.into_owned() }
+    }
+
+    fn top_level_names(&self) -> Vec<(String, String)> {
+        self.cmd
+            .get_subcommands()
+            .map(|s| {
+                let name = s.get_name().trim_end_matches('*').to_owned();
+                let about = s
+                    .get_about()
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_default();
+                (name, about)
+            })
+            .collect()
+    }
+}
+
+impl Completer for SpinShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &RLContext<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix = &line[..pos];
+        let start = prefix.rfind(' ').map(|i| i + 1).unwrap_or(0);
+        let word = &prefix[start..];
+
+        let candidates: Vec<Pair> = self
+            .top_level_names()
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(word))
+            .map(|(name, about)| Pair {
+                display: if about.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{name} -- {about}")
+                },
+                replacement: name,
+            })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+struct AboutHint(String);
+
+impl Hint for AboutHint {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Hinter for SpinShellHelper {
+    type Hint = AboutHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &RLContext<'_>) -> Option<AboutHint> {
+        if pos < line.len() {
+            return None;
+        }
+        let word = line.split(' ').next().unwrap_or("");
+        if word.is_empty() {
+            return None;
+        }
+        self.top_level_names()
+            .into_iter()
+            .find(|(name, _)| name == word)
+            .filter(|(_, about)| !about.is_empty())
+            .map(|(_, about)| AboutHint(format!("  -- {about}")))
+    }
+}
+
+impl Highlighter for SpinShellHelper {}
+impl Validator for SpinShellHelper {}
+impl Helper for SpinShellHelper {}
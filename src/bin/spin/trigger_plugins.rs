@@ -0,0 +1,39 @@
+//! Dynamic dispatch for trigger types that aren't built into the `spin`
+//! binary. `TriggerCommands` only knows about `Http` and `Redis`; anything
+//! else is resolved against an installed `trigger-<name>` plugin at runtime,
+//! the same naming convention `hide_plugin_in_help` already uses to keep
+//! trigger plugins out of the top-level help listing.
+//!
+//! This only wires up `spin trigger <trigger-type>`. `up --trigger` is not
+//! touched here and still only understands the built-in trigger types.
+
+use anyhow::Context;
+use clap::CommandFactory;
+
+use crate::SpinApp;
+
+/// Looks up the plugin name advertising the trigger executor interface for
+/// `trigger_type`, i.e. one named `trigger-<trigger_type>` that is actually
+/// installed (has an executable on disk).
+fn find_trigger_plugin(trigger_type: &str) -> anyhow::Result<String> {
+    let plugin_name = format!("trigger-{trigger_type}");
+    if !crate::plugin_registry::executable_path(&plugin_name).exists() {
+        anyhow::bail!(
+            "no built-in trigger named `{trigger_type}` and no `{plugin_name}` plugin is installed"
+        );
+    }
+    Ok(plugin_name)
+}
+
+/// Dispatches `spin trigger <trigger_type> ...` to the plugin that implements
+/// it, the same way `execute_external_subcommand` hands off to ordinary
+/// plugin subcommands.
+pub async fn run_trigger_plugin(trigger_type: &str, rest: Vec<String>) -> anyhow::Result<()> {
+    let plugin_name = find_trigger_plugin(trigger_type)
+        .with_context(|| format!("failed to resolve trigger `{trigger_type}`"))?;
+
+    let mut command = vec![plugin_name];
+    command.extend(rest);
+
+    spin_cli::commands::external::execute_external_subcommand(command, SpinApp::command()).await
+}